@@ -0,0 +1,504 @@
+// `Cc<T>` is a reference-counted pointer similar to `std::rc::Rc<T>`, except
+// that it can additionally be tracked by a `CcObjectSpace` (see
+// `collect.rs`) so that reference cycles between `Cc`s can be found and
+// broken by the cycle collector, instead of leaking forever.
+
+use crate::collect::ObjectSpace;
+use crate::collect::THREAD_OBJECT_SPACE;
+use crate::debug;
+use crate::mutable_usize::Usize;
+use crate::rcdyn::RcDyn;
+use crate::Trace;
+use crate::Tracer;
+use std::cell::Cell;
+use std::cell::UnsafeCell;
+use std::mem;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::ptr::NonNull;
+
+/// Intrusive linked-list node embedded at the front of every tracked
+/// `CcBoxData`. `CcObjectSpace` only ever touches objects through this
+/// type, so it never needs to know the concrete `T`.
+///
+/// `#[repr(C)]` so that `GcHeaderWithExtras`/`CcBoxData`, whose safety
+/// depends on `gc_header` sitting at offset 0, can actually guarantee that
+/// layout instead of leaving it to rustc's unspecified `repr(Rust)` field
+/// ordering.
+#[repr(C)]
+pub struct GcHeader {
+    pub(crate) next: Cell<*mut GcHeader>,
+    pub(crate) prev: Cell<*mut GcHeader>,
+
+    /// Vtable pointer half of the `&dyn CcDyn` fat pointer for this node's
+    /// `CcBoxData`. The data pointer half is just `&self`, since `GcHeader`
+    /// (by way of `GcHeaderWithExtras`) is always the first field of a
+    /// `CcBoxData`. Set once by `ObjectSpace::insert`.
+    pub(crate) ccdyn_vptr: Cell<*mut ()>,
+}
+
+impl GcHeader {
+    /// Construct a dummy, untracked header. Used for the sentinel nodes of
+    /// `CcObjectSpace`'s generation lists (see `new_gc_list`).
+    pub(crate) fn empty() -> Self {
+        GcHeader {
+            next: Cell::new(std::ptr::null_mut()),
+            prev: Cell::new(std::ptr::null_mut()),
+            ccdyn_vptr: Cell::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// Reconstruct the `&dyn CcDyn` for the `CcBoxData` this header is
+    /// embedded in, using the vtable pointer `ObjectSpace::insert` stashed
+    /// away in `ccdyn_vptr`.
+    pub(crate) fn value(&self) -> &dyn CcDyn {
+        debug_assert!(!self.ccdyn_vptr.get().is_null());
+        unsafe {
+            let fat_ptr: [*mut (); 2] = [self as *const GcHeader as *mut (), self.ccdyn_vptr.get()];
+            mem::transmute(fat_ptr)
+        }
+    }
+}
+
+/// A `GcHeader` plus whatever per-object extra state the owning
+/// `S: ObjectSpace` wants to stash alongside it (for `CcObjectSpace`, the
+/// generation a survivor was promoted to). Must keep `gc_header` as its
+/// first field: `collect.rs` reinterprets bare `&GcHeader`s as
+/// `&GcHeaderWithExtras<S>` to reach `extras`, and `#[repr(C)]` is what
+/// guarantees that offset now that `CcObjectSpace::Extras` is a real
+/// `Cell<u8>` instead of a zero-sized `()`.
+#[repr(C)]
+pub struct GcHeaderWithExtras<S: ObjectSpace> {
+    pub(crate) gc_header: GcHeader,
+    pub(crate) extras: S::Extras,
+}
+
+/// Type-erased view of a tracked object, reachable from a bare `GcHeader`
+/// via `GcHeader::value`. This is what the cycle collector (`collect.rs`)
+/// uses to traverse and ref-count objects without knowing their `T`.
+pub trait CcDyn {
+    /// Current strong reference count.
+    fn gc_ref_count(&self) -> usize;
+
+    /// Visit directly-owned children at the `GcHeader` level. Bridges
+    /// `Trace::trace`'s `RcDyn`-level callback down to the collector's
+    /// `GcHeader`-level one; children that aren't tracked (or are `Weak`,
+    /// which never reaches a tracer at all) are simply skipped.
+    fn gc_traverse(&self, tracer: &mut dyn FnMut(&GcHeader));
+
+    /// Take an extra strong reference, type-erased down to `GcClone` so the
+    /// collector can keep a `CcBoxData` alive without knowing `T`.
+    fn gc_clone(&self) -> Box<dyn GcClone>;
+}
+
+/// A type-erased strong reference used by the collector while dropping
+/// unreachable objects (see `release_unreachable` in `collect.rs`). Keeping
+/// one of these alive keeps the backing `CcBoxData` allocated so its
+/// ref-count metadata stays readable even after `T` has been dropped.
+pub trait GcClone {
+    /// Current strong reference count.
+    fn gc_ref_count(&self) -> usize;
+
+    /// Drop `T` in place without releasing the `CcBoxData` allocation.
+    /// Leaves any `Weak<T>`s pointing at this allocation able to observe
+    /// that the value is gone (see `CcBoxData::value`), instead of
+    /// dangling.
+    fn gc_drop_t(&self);
+}
+
+/// Backing allocation for a `Cc<T>`/`Weak<T>` family. `gc_header` must stay
+/// the first field (see `GcHeaderWithExtras`); `#[repr(C)]` guarantees it,
+/// where plain `repr(Rust)` would only happen to put it there. `value`
+/// is `None` once `T` has been dropped, either by the last strong `Cc`
+/// going away or by the cycle collector force-dropping it; the allocation
+/// itself is only freed once both `strong` and `weak` hit zero.
+#[repr(C)]
+struct CcBoxData<T: Trace, S: ObjectSpace> {
+    gc_header: GcHeaderWithExtras<S>,
+    strong: S::RefCount,
+    weak: Cell<usize>,
+    value: UnsafeCell<Option<T>>,
+}
+
+/// A single-threaded reference-counted pointer that can be tracked by a
+/// [`CcObjectSpace`](struct.CcObjectSpace.html), so reference cycles formed
+/// between `Cc`s can be collected. See the
+/// [module-level docs](index.html) for details.
+pub struct Cc<T: Trace, S: ObjectSpace = crate::collect::CcObjectSpace> {
+    inner: NonNull<CcBoxData<T, S>>,
+}
+
+/// A non-owning reference to the value inside a [`Cc<T>`], similar to
+/// [`std::rc::Weak`]. Unlike a `Cc`, a `Weak` never keeps `T` alive, and is
+/// always invisible to `Trace::trace`/the cycle collector, making it the
+/// natural way to break a cycle by hand (e.g. a child's pointer back to
+/// its parent) without relying on the collector to find it.
+pub struct Weak<T: Trace, S: ObjectSpace = crate::collect::CcObjectSpace> {
+    inner: NonNull<CcBoxData<T, S>>,
+}
+
+impl<T: Trace, S: ObjectSpace> Trace for Weak<T, S> {
+    // A `Weak` is invisible to the tracer, since it never keeps `T` alive,
+    // so the collector must not count it as an edge when looking for cycles.
+    fn trace(&self, _tracer: &mut Tracer) {}
+
+    fn is_type_tracked(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Trace> Cc<T> {
+    /// Constructs a new [`Cc<T>`], tracked by the current thread's
+    /// collector (see [`collect_thread_cycles`](fn.collect_thread_cycles.html)).
+    ///
+    /// Goes through [`CcObjectSpace::create`](struct.CcObjectSpace.html#method.create)
+    /// rather than [`new_in_space`](#method.new_in_space) directly, so the
+    /// thread's generational auto-collection and its
+    /// [`GcConfig`](struct.GcConfig.html)'s `auto_collect_threshold` still
+    /// apply to this, the most common allocation path.
+    pub fn new(value: T) -> Self {
+        THREAD_OBJECT_SPACE.with(|space| space.create(value))
+    }
+
+    /// Constructs a new [`Cc<T>`] that can refer to itself, tracked by the
+    /// current thread's collector.
+    ///
+    /// See [`new_cyclic_in_space`](struct.Cc.html#method.new_cyclic_in_space);
+    /// like [`new`](#method.new), this goes through
+    /// [`CcObjectSpace::create_cyclic`](struct.CcObjectSpace.html#method.create_cyclic)
+    /// so the thread's [`GcConfig`](struct.GcConfig.html) still applies.
+    pub fn new_cyclic<F>(f: F) -> Self
+    where
+        F: FnOnce(&Weak<T>) -> T,
+    {
+        THREAD_OBJECT_SPACE.with(|space| space.create_cyclic(f))
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Cc<T, S> {
+    /// Constructs a new [`Cc<T>`] tracked by `space`.
+    ///
+    /// The returned `Cc<T>` can refer to other `Cc`s tracked by the same
+    /// `space`; referring to a `Cc` from a different space defeats cycle
+    /// collection (the collector only ever traverses one space).
+    pub fn new_in_space(value: T, space: &S) -> Self {
+        let gc_header = GcHeaderWithExtras {
+            gc_header: GcHeader::empty(),
+            extras: space.default_extras(),
+        };
+        let boxed = Box::pin(CcBoxData {
+            gc_header,
+            strong: S::RefCount::default(),
+            // The live strong references collectively hold one implicit
+            // weak reference, mirroring `std::rc::Rc`; it's released (see
+            // `Cc::drop`) once the last strong reference goes away, rather
+            // than whenever the last strong reference happens to finish
+            // running `T::drop`. This keeps a self-referential `Weak`
+            // (e.g. from `new_cyclic`) that's dropped from inside `T::drop`
+            // from freeing the allocation out from under the `Cc::drop`
+            // that's still running on it.
+            weak: Cell::new(1),
+            value: UnsafeCell::new(Some(value)),
+        });
+        boxed.strong.set(1);
+        debug::log(|| ("new", "new"));
+
+        // safety: `boxed` never moves again once leaked; `CcBoxData` is
+        // self-referential-safe (its address is baked into `GcHeader`'s
+        // intrusive list pointers and `ccdyn_vptr`), and the
+        // `Cc`/`Weak`/collector machinery is the sole owner from here on.
+        let inner =
+            unsafe { NonNull::new_unchecked(Box::into_raw(Pin::into_inner_unchecked(boxed))) };
+        let cc = Cc { inner };
+
+        if cc.value().is_type_tracked() {
+            // safety: `inner` was just allocated above and nothing else has
+            // a handle to it yet.
+            let data: &CcBoxData<T, S> = unsafe { inner.as_ref() };
+            space.insert(&data.gc_header, data as &dyn CcDyn);
+            debug::log(|| ("track", "track"));
+        }
+        cc
+    }
+
+    /// Constructs a new [`Cc<T>`] tracked by `space`, giving `f` a
+    /// [`Weak<T>`] to the allocation before `T` itself exists, so `T` can
+    /// build a reference back to its own `Cc` without going through an
+    /// `Option`/`RefCell` dance.
+    ///
+    /// The `Weak` passed to `f` will fail to [`upgrade`](Weak::upgrade) if
+    /// used before `f` returns, since there's no `T` yet for a premature
+    /// `Cc` to point at.
+    pub fn new_cyclic_in_space<F>(f: F, space: &S) -> Self
+    where
+        F: FnOnce(&Weak<T, S>) -> T,
+    {
+        let gc_header = GcHeaderWithExtras {
+            gc_header: GcHeader::empty(),
+            extras: space.default_extras(),
+        };
+        let boxed = Box::pin(CcBoxData {
+            gc_header,
+            strong: S::RefCount::default(),
+            weak: Cell::new(1),
+            value: UnsafeCell::new(None),
+        });
+
+        // safety: same as `new_in_space`: `boxed` never moves again once
+        // leaked.
+        let inner =
+            unsafe { NonNull::new_unchecked(Box::into_raw(Pin::into_inner_unchecked(boxed))) };
+        // This `Weak` accounts for the `weak` count of 1 set above; it's
+        // the only handle to `inner` that exists before `f` runs, and it
+        // drops normally at the end of this function.
+        let weak = Weak { inner };
+
+        let value = f(&weak);
+        let data: &CcBoxData<T, S> = unsafe { inner.as_ref() };
+        // safety: `strong` is still zero, so no `Cc` or the collector has a
+        // handle to dereference `value` concurrently.
+        unsafe { *data.value.get() = Some(value) };
+        data.strong.set(1);
+        // The freshly-minted strong reference takes on the implicit weak
+        // reference described in `new_in_space`; `weak` (the local `Weak`
+        // above, not yet counted towards it) drops normally at the end of
+        // this function and gives its own count back up.
+        data.weak.set(data.weak.get() + 1);
+        debug::log(|| ("new", "new"));
+
+        let cc = Cc { inner };
+        if cc.value().is_type_tracked() {
+            space.insert(&data.gc_header, data as &dyn CcDyn);
+            debug::log(|| ("track", "track"));
+        }
+        cc
+    }
+
+    fn data(&self) -> &CcBoxData<T, S> {
+        unsafe { self.inner.as_ref() }
+    }
+
+    fn value(&self) -> &T {
+        // safety: a live `Cc<T>` always has its value present; only
+        // `Weak::upgrade` observes a dropped value, and it never reaches
+        // here.
+        unsafe { (&*self.data().value.get()).as_ref().unwrap() }
+    }
+
+    /// Creates a new [`Weak`] pointer to this allocation. The value stays
+    /// reachable as `T` through this `Cc` (and any of its clones) as
+    /// usual; the `Weak` itself never keeps `T` alive.
+    pub fn downgrade(&self) -> Weak<T, S> {
+        self.data().weak.set(self.data().weak.get() + 1);
+        Weak { inner: self.inner }
+    }
+
+    /// Number of strong ([`Cc`]) references to this allocation.
+    pub fn strong_count(&self) -> usize {
+        self.data().strong.get()
+    }
+
+    /// Number of [`Weak`] references to this allocation.
+    pub fn weak_count(&self) -> usize {
+        // `self` is a live `Cc`, so `weak` always includes the implicit
+        // reference the strong references hold (see `new_in_space`); hide
+        // it from callers, same as `std::rc::Rc::weak_count`.
+        self.data().weak.get() - 1
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Clone for Cc<T, S> {
+    fn clone(&self) -> Self {
+        let strong = self.data().strong.get() + 1;
+        self.data().strong.set(strong);
+        debug::log(|| ("clone", format!("clone ({})", strong)));
+        Cc { inner: self.inner }
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Deref for Cc<T, S> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value()
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Drop for Cc<T, S> {
+    fn drop(&mut self) {
+        let strong = self.data().strong.get() - 1;
+        self.data().strong.set(strong);
+        // `T` is already gone: the cycle collector force-dropped it (see
+        // `GcClone::gc_drop_t`) and still holds its own strong reference,
+        // which releases the allocation once the collection finishes.
+        if unsafe { &*self.data().value.get() }.is_none() {
+            debug::log(|| ("?", "drop (ignored)"));
+            return;
+        }
+        let tracked = self.value().is_type_tracked();
+        if tracked {
+            debug::log(|| ("drop", format!("drop ({}, tracked)", strong)));
+        } else {
+            debug::log(|| ("drop", format!("drop ({})", strong)));
+        }
+        if strong != 0 {
+            return;
+        }
+        if tracked {
+            let header = unsafe { &self.inner.as_ref().gc_header };
+            S::remove(header);
+            debug::log(|| ("untrack", "untrack"));
+        }
+        drop_value_in_place(self.data());
+        // Release the implicit weak reference the strong references held
+        // collectively (see `new_in_space`) now that the last of them is
+        // gone, and only then check whether the allocation can be freed.
+        // `drop_value_in_place` above runs arbitrary `T::drop` code, which
+        // can drop a `Weak` pointing back at this very allocation (e.g. a
+        // self-referential node from `new_cyclic`); since that `Weak` never
+        // owned the implicit reference, it can't free the allocation out
+        // from under us, and this is the only place that does.
+        let data = self.data();
+        data.weak.set(data.weak.get() - 1);
+        maybe_free(self.inner);
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Weak<T, S> {
+    /// Attempts to upgrade this `Weak` back into a [`Cc<T>`]. Returns
+    /// `None` once `T` is gone: either the last strong `Cc` dropped it, or
+    /// the cycle collector force-dropped it while releasing an unreachable
+    /// component (see `release_unreachable` in `collect.rs`).
+    pub fn upgrade(&self) -> Option<Cc<T, S>> {
+        let data = unsafe { self.inner.as_ref() };
+        // safety: read-only peek at whether `value` is still populated;
+        // `drop_value_in_place` is the only thing that clears it, and it
+        // only runs once no `Cc` will dereference `T` again: either
+        // `strong` has reached zero for good, or the cycle collector found
+        // the value unreachable.
+        if unsafe { &*data.value.get() }.is_none() {
+            return None;
+        }
+        let strong = data.strong.get() + 1;
+        data.strong.set(strong);
+        debug::log(|| ("clone", format!("clone ({})", strong)));
+        Some(Cc { inner: self.inner })
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Clone for Weak<T, S> {
+    fn clone(&self) -> Self {
+        let data = unsafe { self.inner.as_ref() };
+        data.weak.set(data.weak.get() + 1);
+        Weak { inner: self.inner }
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Drop for Weak<T, S> {
+    fn drop(&mut self) {
+        let data = unsafe { self.inner.as_ref() };
+        data.weak.set(data.weak.get() - 1);
+        maybe_free(self.inner);
+    }
+}
+
+/// Drop `T` in place, leaving the `CcBoxData` allocation itself alive for
+/// any outstanding `Weak`s (or the collector's own extra reference, while
+/// it's mid-collection) to keep observing ref-count metadata through.
+fn drop_value_in_place<T: Trace, S: ObjectSpace>(data: &CcBoxData<T, S>) {
+    // safety: only called once `strong` has reached zero for good, or by
+    // the cycle collector on a value it found unreachable, so no other
+    // `Cc<T>` can be dereferencing `value` concurrently.
+    unsafe { &mut *data.value.get() }.take();
+}
+
+/// Release the `CcBoxData` allocation once both strong and weak counts
+/// have hit zero.
+fn maybe_free<T: Trace, S: ObjectSpace>(inner: NonNull<CcBoxData<T, S>>) {
+    let data = unsafe { inner.as_ref() };
+    if data.strong.get() == 0 && data.weak.get() == 0 {
+        // safety: no `Cc`/`Weak` refers to this allocation any more.
+        unsafe { drop(Box::from_raw(inner.as_ptr())) };
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> CcDyn for CcBoxData<T, S> {
+    fn gc_ref_count(&self) -> usize {
+        self.strong.get()
+    }
+
+    fn gc_traverse(&self, tracer: &mut dyn FnMut(&GcHeader)) {
+        debug::log(|| ("gc_traverse", "gc_traverse"));
+        // safety: tracing never mutates `value`, and only runs while at
+        // least one strong reference (the collector's own, or a live
+        // `Cc`) keeps it populated.
+        if let Some(value) = unsafe { &*self.value.get() }.as_ref() {
+            let mut rc_tracer = |rc_dyn: &dyn RcDyn| {
+                if let Some(header) = rc_dyn.gc_header() {
+                    debug::log(|| ("trace", "trace"));
+                    tracer(header);
+                }
+            };
+            value.trace(&mut rc_tracer as &mut Tracer);
+        }
+    }
+
+    fn gc_clone(&self) -> Box<dyn GcClone> {
+        self.strong.set(self.strong.get() + 1);
+        // This extra reference is the collector's commitment to force-drop
+        // `T` below, so log it as that step rather than an ordinary `clone`.
+        debug::log(|| ("gc_prepare_drop", "gc_prepare_drop"));
+        Box::new(GcCloneHandle {
+            inner: self as *const Self,
+        })
+    }
+}
+
+/// Opaque extra strong reference handed to the collector via
+/// `CcDyn::gc_clone`. Exists only so `release_unreachable` can keep a
+/// `CcBoxData` (and its ref-count metadata) alive across the `T::drop`
+/// calls it triggers, without knowing `T`.
+struct GcCloneHandle<T: Trace, S: ObjectSpace> {
+    inner: *const CcBoxData<T, S>,
+}
+
+impl<T: Trace, S: ObjectSpace> GcClone for GcCloneHandle<T, S> {
+    fn gc_ref_count(&self) -> usize {
+        // safety: holding this handle is itself a strong reference, so the
+        // allocation is still valid.
+        unsafe { &*self.inner }.strong.get()
+    }
+
+    fn gc_drop_t(&self) {
+        let data = unsafe { &*self.inner };
+        // Unlink before running `T::drop` below: this handle's own `Drop`
+        // only runs once the whole cycle has been force-dropped (see
+        // `release_unreachable`), too late to protect other members' own
+        // `gc_traverse`.
+        S::remove(&data.gc_header);
+        debug::log(|| ("untrack", "untrack"));
+        debug::log(|| ("gc_force_drop", "gc_force_drop"));
+        drop_value_in_place(data);
+    }
+}
+
+impl<T: Trace, S: ObjectSpace> Drop for GcCloneHandle<T, S> {
+    fn drop(&mut self) {
+        // safety: `self.inner` points at a live `CcBoxData`; dropping this
+        // extra reference may free it.
+        let data = unsafe { &*self.inner };
+        let strong = data.strong.get() - 1;
+        data.strong.set(strong);
+        if strong == 0 {
+            // Last strong reference gone; release the implicit weak
+            // reference the strong references collectively held (see
+            // `Cc::new_in_space`).
+            debug::log(|| ("gc_mark_for_release", "gc_mark_for_release"));
+            data.weak.set(data.weak.get() - 1);
+        }
+        let inner = unsafe { NonNull::new_unchecked(self.inner as *mut CcBoxData<T, S>) };
+        if data.strong.get() == 0 && data.weak.get() == 0 {
+            debug::log(|| ("drop_release", "drop (release)"));
+        }
+        maybe_free(inner);
+    }
+}
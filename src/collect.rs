@@ -2,7 +2,10 @@
 //
 // [1]: https://github.com/python/cpython/blob/v3.8.0/Modules/gcmodule.c
 
-// NOTE: Consider adding generation support if necessary. It won't be too hard.
+// Generational collection also follows cpython 3.8's approach: each
+// generation is its own linked list, younger generations are collected far
+// more often than older ones, and survivors of a collection are promoted to
+// the next older generation.
 
 use crate::cc::CcDyn;
 use crate::cc::GcClone;
@@ -12,6 +15,7 @@ use crate::debug;
 use crate::mutable_usize::Usize;
 use crate::Cc;
 use crate::Trace;
+use crate::Weak;
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::marker::PhantomData;
@@ -19,6 +23,20 @@ use std::mem;
 use std::ops::Deref;
 use std::pin::Pin;
 
+/// Number of generations tracked by a [`CcObjectSpace`]. Generation `0` is
+/// the youngest; objects are promoted towards `NUM_GENERATIONS - 1` as they
+/// survive collections.
+const NUM_GENERATIONS: usize = 3;
+
+/// Number of `create()` calls that triggers a generation-0 collection.
+/// Chosen to match cpython's default `gc.get_threshold()[0]`.
+const GEN0_ALLOC_THRESHOLD: usize = 700;
+
+/// Number of generation-`N` collections that triggers a collection of
+/// generation `N + 1`. Chosen to match cpython's default thresholds for
+/// generations 1 and 2.
+const GEN_TICK_THRESHOLD: usize = 10;
+
 /// A collection of [`Cc`](struct.Cc.html)s that might form cycles with one
 /// another.
 ///
@@ -45,8 +63,34 @@ use std::pin::Pin;
 ///
 /// Use [`Cc::new_in_space`](struct.Cc.html#method.new_in_space).
 pub struct CcObjectSpace {
-    /// Linked list to the tracked objects.
-    pub(crate) list: RefCell<Pin<Box<GcHeader>>>,
+    /// One linked list per generation. Index `0` is the youngest
+    /// generation; new objects are always inserted there.
+    generations: [RefCell<Pin<Box<GcHeader>>>; NUM_GENERATIONS],
+
+    /// Number of `create()` calls since the last generation-0 collection.
+    gen0_allocs: Cell<usize>,
+
+    /// `gen_ticks[i]` counts how many times generation `i` has been
+    /// collected since generation `i + 1` was last collected. Reaching
+    /// `GEN_TICK_THRESHOLD` promotes the next auto-collection to include
+    /// generation `i + 1` as well. The last slot is unused.
+    gen_ticks: [Cell<usize>; NUM_GENERATIONS],
+
+    /// Number of `create()` calls since `maybe_auto_collect_threshold` last
+    /// checked whether `count_tracked()` grew by the threshold. Just a cheap
+    /// gate on how often that O(`count_tracked()`) check runs; it does not
+    /// by itself decide whether to collect.
+    auto_collect_allocs: Cell<usize>,
+
+    /// `count_tracked()` right after the last collection that
+    /// `maybe_auto_collect_threshold` ran, the baseline it measures growth
+    /// against, so a steady-state alloc/free workload that keeps cycling
+    /// through the same `auto_collect_allocs` allowance never collects.
+    auto_collect_last_tracked: Cell<usize>,
+
+    config: RefCell<GcConfig>,
+
+    stats: RefCell<GcStats>,
 
     /// Mark `ObjectSpace` as `!Send` and `!Sync`. This enforces thread-exclusive
     /// access to the linked list so methods can use `&self` instead of
@@ -54,6 +98,72 @@ pub struct CcObjectSpace {
     _phantom: PhantomData<Cc<()>>,
 }
 
+/// Configuration controlling when and how a [`CcObjectSpace`] collects
+/// cyclic garbage. Set it with
+/// [`CcObjectSpace::set_config`](struct.CcObjectSpace.html#method.set_config)
+/// or [`set_thread_gc_config`](fn.set_thread_gc_config.html).
+#[derive(Clone, Debug)]
+pub struct GcConfig {
+    /// If set, dropping the owning `CcObjectSpace` skips the final
+    /// `collect_cycles()` and just leaks any remaining `CcBox`es instead.
+    /// Useful when a program is tearing down and exiting anyway, where the
+    /// final collection is pure overhead.
+    pub leak_on_drop: bool,
+
+    /// If `Some(n)`, `create()` automatically runs `collect_cycles()` once
+    /// `count_tracked()` has grown by roughly `n` since the last collection.
+    /// `None` (the default) disables this; callers collect manually, e.g.
+    /// via `collect_cycles()` or `collect_thread_cycles()`.
+    pub auto_collect_threshold: Option<usize>,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            leak_on_drop: false,
+            auto_collect_threshold: None,
+        }
+    }
+}
+
+impl GcConfig {
+    /// Builder-style setter for `leak_on_drop`.
+    pub fn with_leak_on_drop(mut self, leak_on_drop: bool) -> Self {
+        self.leak_on_drop = leak_on_drop;
+        self
+    }
+
+    /// Builder-style setter for `auto_collect_threshold`.
+    pub fn with_auto_collect_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.auto_collect_threshold = threshold;
+        self
+    }
+}
+
+/// Cumulative collection statistics for a [`CcObjectSpace`], see
+/// [`CcObjectSpace::stats`](struct.CcObjectSpace.html#method.stats). Useful
+/// to profile whether a workload actually produces cycles, and how often
+/// the collector is doing useful work, which is otherwise only visible
+/// through `debug::log`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GcStats {
+    /// Total number of objects ever tracked by this space.
+    pub objects_tracked: usize,
+
+    /// Peak value of `objects_tracked - objects_released`, i.e. the
+    /// highest number of tracked objects seen at once. Not a true live-set
+    /// peak: objects dropped acyclically never decrement it, since the
+    /// ordinary drop path (`Cc::drop`) has no handle back to the space
+    /// that tracked them.
+    pub peak_tracked_objects: usize,
+
+    /// Number of times `collect_cycles`/`collect_generation` has run.
+    pub collections: usize,
+
+    /// Total number of unreachable objects released across all collections.
+    pub objects_released: usize,
+}
+
 /// This is a private type.
 pub trait ObjectSpace: 'static + Sized {
     type RefCount: Usize;
@@ -62,7 +172,9 @@ pub trait ObjectSpace: 'static + Sized {
     /// Insert "header" and "value" to the linked list.
     fn insert(&self, header: &GcHeaderWithExtras<Self>, value: &dyn CcDyn);
 
-    /// Remove from linked list.
+    /// Remove from linked list. Takes no `&self`: `Cc<T>::drop` doesn't
+    /// keep a handle back to the `ObjectSpace` it was created in, so this
+    /// has to work from the header alone.
     fn remove(header: &GcHeaderWithExtras<Self>);
 
     fn default_extras(&self) -> Self::Extras;
@@ -70,11 +182,21 @@ pub trait ObjectSpace: 'static + Sized {
 
 impl ObjectSpace for CcObjectSpace {
     type RefCount = Cell<usize>;
-    type Extras = ();
+    // The generation an object currently lives in. New objects always start
+    // at generation 0; `collect_generation` promotes survivors from there.
+    type Extras = Cell<u8>;
 
     fn insert(&self, header: &GcHeaderWithExtras<Self>, value: &dyn CcDyn) {
+        header.extras.set(0);
+
+        let mut stats = self.stats.borrow_mut();
+        stats.objects_tracked += 1;
+        let net = stats.objects_tracked - stats.objects_released;
+        stats.peak_tracked_objects = stats.peak_tracked_objects.max(net);
+        drop(stats);
+
         let header: &GcHeader = &header.gc_header;
-        let prev: &GcHeader = &self.list.borrow();
+        let prev: &GcHeader = &self.generations[0].borrow();
         debug_assert!(header.next.get().is_null());
         let next = prev.next.get();
         header.prev.set(prev.deref());
@@ -105,35 +227,69 @@ impl ObjectSpace for CcObjectSpace {
     }
 
     fn default_extras(&self) -> Self::Extras {
-        ()
+        Cell::new(0)
     }
 }
 
 impl Default for CcObjectSpace {
     /// Constructs an empty [`ObjectSpace`](struct.ObjectSpace.html).
     fn default() -> Self {
-        let header = new_gc_list();
         Self {
-            list: RefCell::new(header),
+            // NUM_GENERATIONS is small and fixed, so the lists are spelled
+            // out instead of using `[x; N]`, which requires `Copy`.
+            generations: [
+                RefCell::new(new_gc_list()),
+                RefCell::new(new_gc_list()),
+                RefCell::new(new_gc_list()),
+            ],
+            gen0_allocs: Cell::new(0),
+            gen_ticks: [Cell::new(0), Cell::new(0), Cell::new(0)],
+            auto_collect_allocs: Cell::new(0),
+            auto_collect_last_tracked: Cell::new(0),
+            config: RefCell::new(GcConfig::default()),
+            stats: RefCell::new(GcStats::default()),
             _phantom: PhantomData,
         }
     }
 }
 
 impl CcObjectSpace {
-    /// Count objects tracked by this [`ObjectSpace`](struct.ObjectSpace.html).
+    /// Count objects tracked by this [`ObjectSpace`](struct.ObjectSpace.html),
+    /// across all generations.
     pub fn count_tracked(&self) -> usize {
-        let list: &GcHeader = &self.list.borrow();
         let mut count = 0;
-        visit_list(list, |_| count += 1);
+        for generation in &self.generations {
+            let list: &GcHeader = &generation.borrow();
+            visit_list(list, |_| count += 1);
+        }
         count
     }
 
     /// Collect cyclic garbage tracked by this [`ObjectSpace`](struct.ObjectSpace.html).
-    /// Return the number of objects collected.
+    /// This collects every generation. Return the number of objects collected.
     pub fn collect_cycles(&self) -> usize {
-        let list: &GcHeader = &self.list.borrow();
-        collect_list(list)
+        self.collect_generation(NUM_GENERATIONS - 1)
+    }
+
+    /// Collect cyclic garbage in generation `generation` and all younger
+    /// generations (generations are numbered from `0`, the youngest).
+    /// Survivors are promoted to the next older generation, or stay in the
+    /// oldest generation if there isn't one. Return the number of objects
+    /// collected.
+    ///
+    /// References from an *uncollected* older generation into the
+    /// generations being collected are never traversed by this pass, so
+    /// they keep the ref count of the objects they point to above zero --
+    /// exactly like cpython's generational gc treats older-generation
+    /// pointers as roots.
+    pub fn collect_generation(&self, generation: usize) -> usize {
+        let oldest = generation.min(NUM_GENERATIONS - 1);
+        self.merge_young_generations(oldest);
+        let list: &GcHeader = &self.generations[0].borrow();
+        let count = collect_list(list, self);
+        let target = (oldest + 1).min(NUM_GENERATIONS - 1);
+        self.promote_survivors(target);
+        count
     }
 
     /// Constructs a new [`Cc<T>`](struct.Cc.html) in this
@@ -144,19 +300,177 @@ impl CcObjectSpace {
     ///
     /// If a `Cc` refers to another `Cc` in another
     /// [`ObjectSpace`](struct.ObjectSpace.html), the cyclic collector will not
-    /// be able to collect cycles.
+    /// be able to collect cycles, unless the two spaces are first combined
+    /// with [`merge`](#method.merge).
     pub fn create<T: Trace>(&self, value: T) -> Cc<T> {
         // `&mut self` ensures thread-exclusive access.
-        Cc::new_in_space(value, self)
+        let cc = Cc::new_in_space(value, self);
+        self.maybe_auto_collect_young();
+        self.maybe_auto_collect_threshold();
+        cc
+    }
+
+    /// Constructs a new [`Cc<T>`](struct.Cc.html) in this
+    /// [`ObjectSpace`](struct.ObjectSpace.html) that can refer to itself.
+    ///
+    /// See [`Cc::new_cyclic_in_space`](struct.Cc.html#method.new_cyclic_in_space).
+    pub fn create_cyclic<T: Trace, F>(&self, f: F) -> Cc<T>
+    where
+        F: FnOnce(&Weak<T>) -> T,
+    {
+        let cc = Cc::new_cyclic_in_space(f, self);
+        self.maybe_auto_collect_young();
+        self.maybe_auto_collect_threshold();
+        cc
+    }
+
+    /// Replace this space's [`GcConfig`].
+    pub fn set_config(&self, config: GcConfig) {
+        *self.config.borrow_mut() = config;
+    }
+
+    /// Get a copy of this space's current [`GcConfig`].
+    pub fn config(&self) -> GcConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Get a copy of this space's cumulative [`GcStats`].
+    pub fn stats(&self) -> GcStats {
+        *self.stats.borrow()
+    }
+
+    /// Reset this space's [`GcStats`] back to zero.
+    pub fn reset_stats(&self) {
+        *self.stats.borrow_mut() = GcStats::default();
     }
 
-    // TODO: Consider implementing "merge" or method to collect multiple spaces
-    // together, to make it easier to support generational collection.
+    /// Merge `other`'s tracked objects into `self`, generation by
+    /// generation, and consume `other` without running its `Drop`-time
+    /// `collect_cycles()`.
+    ///
+    /// A `Cc` in one `CcObjectSpace` referring to a `Cc` in another can
+    /// never have that cycle collected, since each space's collector only
+    /// traverses its own lists (see [`create`](#method.create)'s docs).
+    /// Merging the two spaces first, then calling
+    /// [`collect_cycles`](#method.collect_cycles) on the result, reclaims
+    /// cycles that used to span both. Splicing preserves each object's
+    /// generation, and `other`'s `GcStats` are folded into `self`'s,
+    /// though its `auto_collect_threshold` cadence is not.
+    ///
+    /// Takes `other` by value rather than `&other`, so a space can never
+    /// be merged into itself.
+    pub fn merge(&self, other: CcObjectSpace) {
+        for generation in 0..NUM_GENERATIONS {
+            let src: &GcHeader = &other.generations[generation].borrow();
+            let dst: &GcHeader = &self.generations[generation].borrow();
+            splice_list(src, dst);
+        }
+
+        let mut stats = self.stats.borrow_mut();
+        let other_stats = other.stats.borrow();
+        stats.objects_tracked += other_stats.objects_tracked;
+        stats.objects_released += other_stats.objects_released;
+        stats.collections += other_stats.collections;
+        stats.peak_tracked_objects = stats.peak_tracked_objects.max(other_stats.peak_tracked_objects);
+        drop(stats);
+        drop(other_stats);
+
+        // `other`'s generation lists are now empty and its objects are
+        // reachable from `self`, so letting it `Drop` normally would run a
+        // pointless `collect_cycles()` and must not touch those objects.
+        // Forgetting it leaks its (now-empty) dummy list-head allocations,
+        // one small `GcHeader` per generation.
+        mem::forget(other);
+    }
+
+    /// If `GcConfig::auto_collect_threshold` is set, bump the allocation
+    /// counter it drives, and once it's crossed, run a full
+    /// `collect_cycles()`, but only if `count_tracked()` grew by at least
+    /// the threshold since the last such collection; `auto_collect_allocs`
+    /// is just a cheap gate on how often this pays for an
+    /// O(`count_tracked()`) walk, not the thing that decides whether to
+    /// collect.
+    fn maybe_auto_collect_threshold(&self) {
+        let threshold = match self.config.borrow().auto_collect_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let allocs = self.auto_collect_allocs.get() + 1;
+        if allocs < threshold {
+            self.auto_collect_allocs.set(allocs);
+            return;
+        }
+        self.auto_collect_allocs.set(0);
+
+        let tracked = self.count_tracked();
+        if tracked >= self.auto_collect_last_tracked.get() + threshold {
+            self.collect_cycles();
+            self.auto_collect_last_tracked.set(self.count_tracked());
+        }
+    }
+
+    /// Bump the generation-0 allocation counter and, once
+    /// `GEN0_ALLOC_THRESHOLD` is crossed, run a collection. Which
+    /// generations get collected follows the same tick-counter cascade
+    /// cpython uses: every collection of generation `N` ticks generation
+    /// `N + 1`'s counter, and `GEN_TICK_THRESHOLD` ticks trigger a
+    /// collection of generation `N + 1` too.
+    fn maybe_auto_collect_young(&self) {
+        let allocs = self.gen0_allocs.get() + 1;
+        if allocs < GEN0_ALLOC_THRESHOLD {
+            self.gen0_allocs.set(allocs);
+            return;
+        }
+        self.gen0_allocs.set(0);
+
+        let mut oldest = 0;
+        for generation in 0..NUM_GENERATIONS - 1 {
+            let ticks = self.gen_ticks[generation].get() + 1;
+            if ticks < GEN_TICK_THRESHOLD {
+                self.gen_ticks[generation].set(ticks);
+                break;
+            }
+            self.gen_ticks[generation].set(0);
+            oldest = generation + 1;
+        }
+        self.collect_generation(oldest);
+    }
+
+    /// Splice generations `1..=oldest` onto generation 0's list, so a
+    /// single `collect_list` pass over generation 0 sees them as one
+    /// connected graph. Generations above `oldest` are left untouched.
+    fn merge_young_generations(&self, oldest: usize) {
+        for generation in 1..=oldest {
+            let src: &GcHeader = &self.generations[generation].borrow();
+            let dst: &GcHeader = &self.generations[0].borrow();
+            splice_list(src, dst);
+        }
+    }
+
+    /// After a collection, whatever remains linked into generation 0's list
+    /// are the survivors. Tag them with their new generation and move them
+    /// there.
+    fn promote_survivors(&self, target: usize) {
+        let list: &GcHeader = &self.generations[0].borrow();
+        visit_list(list, |header| unsafe {
+            // safety: every node linked into a `CcObjectSpace`'s generations
+            // was built as a `GcHeaderWithExtras<CcObjectSpace>` (see
+            // `Cc::new_in_space`), and `gc_header` is its first field, so
+            // the pointer is valid to reinterpret.
+            header_extras(header).set(target as u8);
+        });
+        if target != 0 {
+            let dst: &GcHeader = &self.generations[target].borrow();
+            splice_list(list, dst);
+        }
+    }
 }
 
 impl Drop for CcObjectSpace {
     fn drop(&mut self) {
-        self.collect_cycles();
+        if !self.config.borrow().leak_on_drop {
+            self.collect_cycles();
+        }
     }
 }
 
@@ -175,6 +489,30 @@ pub fn count_thread_tracked() -> usize {
     THREAD_OBJECT_SPACE.with(|list| list.count_tracked())
 }
 
+/// Set the [`GcConfig`] used by the current thread's collector, created by
+/// [`Cc::new`](struct.Cc.html#method.new).
+pub fn set_thread_gc_config(config: GcConfig) {
+    THREAD_OBJECT_SPACE.with(|space| space.set_config(config))
+}
+
+/// Get the [`GcConfig`] used by the current thread's collector, created by
+/// [`Cc::new`](struct.Cc.html#method.new).
+pub fn thread_gc_config() -> GcConfig {
+    THREAD_OBJECT_SPACE.with(|space| space.config())
+}
+
+/// Get the [`GcStats`] of the current thread's collector, created by
+/// [`Cc::new`](struct.Cc.html#method.new).
+pub fn thread_gc_stats() -> GcStats {
+    THREAD_OBJECT_SPACE.with(|space| space.stats())
+}
+
+/// Reset the [`GcStats`] of the current thread's collector, created by
+/// [`Cc::new`](struct.Cc.html#method.new).
+pub fn reset_thread_gc_stats() {
+    THREAD_OBJECT_SPACE.with(|space| space.reset_stats())
+}
+
 thread_local!(pub(crate) static THREAD_OBJECT_SPACE: CcObjectSpace = CcObjectSpace::default());
 
 /// Create an empty linked list with a dummy GcHeader.
@@ -186,11 +524,42 @@ fn new_gc_list() -> Pin<Box<GcHeader>> {
     pinned
 }
 
+/// Reinterpret a bare `GcHeader` list node as the
+/// `GcHeaderWithExtras<CcObjectSpace>` it was allocated as, to reach its
+/// `extras` (generation) slot from a plain list traversal.
+unsafe fn header_extras(header: &GcHeader) -> &Cell<u8> {
+    let with_extras = header as *const GcHeader as *const GcHeaderWithExtras<CcObjectSpace>;
+    &(*with_extras).extras
+}
+
+/// Splice all entries of `src`'s circular list onto the end of `dst`'s
+/// circular list, leaving `src` empty. Both must be dummy list heads (see
+/// `new_gc_list`).
+fn splice_list(src: &GcHeader, dst: &GcHeader) {
+    let first = src.next.get();
+    if first == src {
+        // `src` has no real entries.
+        return;
+    }
+    let last = src.prev.get();
+    let dst_last = dst.prev.get();
+    unsafe {
+        // safety: both lists are maintained circular linked lists, so all
+        // of these pointers are valid.
+        (&*dst_last).next.set(first);
+        (&*first).prev.set(dst_last);
+        (&*last).next.set(dst);
+        dst.prev.set(last);
+    }
+    src.next.set(src);
+    src.prev.set(src);
+}
+
 /// Scan the specified linked list. Collect cycles.
-fn collect_list(list: &GcHeader) -> usize {
+fn collect_list(list: &GcHeader, space: &CcObjectSpace) -> usize {
     update_refs(list);
     subtract_refs(list);
-    release_unreachable(list)
+    release_unreachable(list, space)
 }
 
 /// Visit the linked list.
@@ -259,7 +628,7 @@ fn mark_reachable(list: &GcHeader) {
 }
 
 /// Release unreachable objects in the linked list.
-fn release_unreachable(list: &GcHeader) -> usize {
+fn release_unreachable(list: &GcHeader, space: &CcObjectSpace) -> usize {
     // Mark reachable objects. For example, A refers B. A's gc_ref_count
     // is 1 while B's gc_ref_count is 0. In this case B should be revived
     // by A's non-zero gc_ref_count.
@@ -276,6 +645,11 @@ fn release_unreachable(list: &GcHeader) -> usize {
 
     debug::log(|| ("collect", format!("{} unreachable objects", count)));
 
+    let mut stats = space.stats.borrow_mut();
+    stats.collections += 1;
+    stats.objects_released += count;
+    drop(stats);
+
     // Build a list of what to drop. The collecting steps change the linked list
     // so `visit_list` cannot be used.
     //
@@ -1,5 +1,5 @@
 use crate::debug;
-use crate::{collect, Cc, Trace, Tracer};
+use crate::{collect, Cc, Trace, Tracer, Weak};
 use quickcheck::quickcheck;
 use std::cell::RefCell;
 use std::ops::Deref;
@@ -232,9 +232,306 @@ collect: 2 unreachable objects
     );
 }
 
+/// Guards against a future edit to `cc.rs` silently dropping one of the
+/// `debug::log` calls along the force-drop path (`gc_traverse`, `gc_clone`,
+/// `gc_drop_t`, `GcCloneHandle::drop`). Unlike the exact-sequence
+/// assertions above, this only checks that each event name still appears
+/// somewhere in the log, so it doesn't depend on reproducing the capture
+/// format's exact grouping/ordering rules from memory.
+#[test]
+fn test_cycle_collection_logs_every_force_drop_event() {
+    let log = debug::capture_log(|| test_small_graph(1, &[0x00, 0x00, 0x00]));
+    for event in &[
+        "gc_traverse",
+        "trace",
+        "gc_prepare_drop",
+        "untrack",
+        "gc_force_drop",
+        "gc_mark_for_release",
+        "drop (release)",
+    ] {
+        assert!(
+            log.contains(event),
+            "expected log to contain {:?}, got: {}",
+            event,
+            log
+        );
+    }
+}
+
+#[test]
+fn test_weak_upgrade_while_strong_alive() {
+    let a = Cc::new(RefCell::new(0));
+    let w = a.downgrade();
+    let upgraded = w.upgrade().expect("value is still alive");
+    *upgraded.borrow_mut() = 1;
+    assert_eq!(*a.borrow(), 1);
+}
+
+#[test]
+fn test_weak_upgrade_after_strong_dropped() {
+    let a = Cc::new(RefCell::new(0));
+    let w = a.downgrade();
+    drop(a);
+    assert!(w.upgrade().is_none());
+}
+
+#[test]
+fn test_weak_breaks_cycle_without_collection() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    struct Node {
+        // A `Weak` parent pointer is invisible to `trace`, so a
+        // parent/child pair never forms a cycle the collector needs to
+        // find; plain ref-counting drops both once the owning `Cc`s go
+        // out of scope.
+        parent: RefCell<Option<Weak<Node>>>,
+        child: RefCell<Option<Cc<Node>>>,
+    }
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.child.trace(tracer);
+        }
+    }
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, SeqCst);
+        }
+    }
+
+    assert_eq!(collect::collect_thread_cycles(), 0);
+    {
+        let parent = Cc::new(Node {
+            parent: RefCell::new(None),
+            child: RefCell::new(None),
+        });
+        let child = Cc::new(Node {
+            parent: RefCell::new(Some(parent.downgrade())),
+            child: RefCell::new(None),
+        });
+        *parent.child.borrow_mut() = Some(child.clone());
+    }
+    assert_eq!(DROPPED.load(SeqCst), 2);
+    assert_eq!(collect::collect_thread_cycles(), 0);
+}
+
+#[test]
+fn test_new_cyclic_self_reference() {
+    struct Node {
+        me: RefCell<Option<Weak<Node>>>,
+    }
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.me.trace(tracer);
+        }
+    }
+
+    let node = Cc::new_cyclic(|me| Node {
+        me: RefCell::new(Some(me.clone())),
+    });
+    let upgraded = node
+        .me
+        .borrow()
+        .as_ref()
+        .unwrap()
+        .upgrade()
+        .expect("node is still alive");
+    assert!(std::ptr::eq(&*node, &*upgraded));
+}
+
+#[test]
+fn test_new_cyclic_weak_fails_to_upgrade_during_construction() {
+    struct Node;
+    impl Trace for Node {}
+
+    let mut upgraded_during_construction = true;
+    let node = Cc::new_cyclic(|me| {
+        upgraded_during_construction = me.upgrade().is_some();
+        Node
+    });
+    assert!(!upgraded_during_construction);
+    drop(node);
+}
+
+#[test]
+fn test_collect_generation_promotes_survivors() {
+    type List = Cc<RefCell<Vec<Box<dyn Trace>>>, collect::CcObjectSpace>;
+
+    let space = collect::CcObjectSpace::default();
+    let x: List = space.create(RefCell::new(Vec::new()));
+    x.borrow_mut().push(Box::new(x.clone()));
+
+    // `x` stays externally reachable through the local binding across two
+    // generational passes, so each one promotes it without collecting it,
+    // exactly like cpython's generational gc.
+    assert_eq!(space.collect_generation(0), 0);
+    assert_eq!(space.collect_generation(1), 0);
+    assert_eq!(space.count_tracked(), 1);
+
+    // Dropping the local binding leaves only the cycle's own self
+    // reference, wherever the two passes above promoted it to; a full
+    // collection must still find and release it.
+    drop(x);
+    assert_eq!(space.collect_cycles(), 1);
+}
+
+#[test]
+fn test_collect_generation_skips_older_generation() {
+    type List = Cc<RefCell<Vec<Box<dyn Trace>>>, collect::CcObjectSpace>;
+
+    let space = collect::CcObjectSpace::default();
+
+    // Promote `old` into an older generation before the cycle below even
+    // exists, while it's still externally reachable.
+    let old: List = space.create(RefCell::new(Vec::new()));
+    assert_eq!(space.collect_generation(0), 0);
+
+    let a: List = space.create(RefCell::new(Vec::new()));
+    let b: List = space.create(RefCell::new(Vec::new()));
+    a.borrow_mut().push(Box::new(b.clone()));
+    b.borrow_mut().push(Box::new(a.clone()));
+    old.borrow_mut().push(Box::new(a.clone()));
+    drop(a);
+    drop(b);
+
+    // A gen-0-only pass never traverses `old`'s list (it was promoted
+    // above), so its reference into the `a`/`b` cycle is invisible to
+    // `subtract_refs`: they look reachable and survive, exactly like
+    // cpython treats older generations as roots for younger ones.
+    assert_eq!(space.collect_generation(0), 0);
+    assert_eq!(space.count_tracked(), 3);
+
+    // Drop `old`'s own reference; the cycle is now truly unreachable, and
+    // only a collection that reaches `old`'s generation finds it.
+    old.borrow_mut().clear();
+    assert_eq!(space.collect_cycles(), 2);
+}
+
+#[test]
+fn test_leak_on_drop_skips_final_collection() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+    struct Node(RefCell<Vec<Box<dyn Trace>>>);
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.0.trace(tracer);
+        }
+    }
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.store(true, SeqCst);
+        }
+    }
+
+    let space = collect::CcObjectSpace::default();
+    space.set_config(collect::GcConfig::default().with_leak_on_drop(true));
+    {
+        let a: Cc<Node, collect::CcObjectSpace> = space.create(Node(RefCell::new(Vec::new())));
+        a.0.borrow_mut().push(Box::new(a.clone()));
+    }
+    drop(space);
+    assert!(!DROPPED.load(SeqCst));
+}
+
+#[test]
+fn test_default_config_collects_on_drop() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+    struct Node(RefCell<Vec<Box<dyn Trace>>>);
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.0.trace(tracer);
+        }
+    }
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.store(true, SeqCst);
+        }
+    }
+
+    let space = collect::CcObjectSpace::default();
+    {
+        let a: Cc<Node, collect::CcObjectSpace> = space.create(Node(RefCell::new(Vec::new())));
+        a.0.borrow_mut().push(Box::new(a.clone()));
+    }
+    drop(space);
+    assert!(DROPPED.load(SeqCst));
+}
+
+#[test]
+fn test_auto_collect_threshold_triggers_on_growth() {
+    type List = Cc<RefCell<Vec<Box<dyn Trace>>>, collect::CcObjectSpace>;
+
+    let space = collect::CcObjectSpace::default();
+    space.set_config(collect::GcConfig::default().with_auto_collect_threshold(Some(2)));
+
+    for _ in 0..4 {
+        let a: List = space.create(RefCell::new(Vec::new()));
+        let b: List = space.create(RefCell::new(Vec::new()));
+        a.borrow_mut().push(Box::new(b.clone()));
+        b.borrow_mut().push(Box::new(a.clone()));
+        // Each iteration's pair becomes an unreachable cycle once these
+        // bindings drop at the end of the loop body, so by the next
+        // iteration's second `create()` call, `count_tracked()` has grown
+        // enough to trigger an automatic `collect_cycles()`.
+    }
+
+    assert_eq!(space.stats().collections, 4);
+    // The last iteration's pair was never followed by another `create()`
+    // call, so it's still sitting there uncollected.
+    assert_eq!(space.collect_cycles(), 2);
+}
+
+#[test]
+fn test_gc_stats_tracks_collections_and_resets() {
+    type List = Cc<RefCell<Vec<Box<dyn Trace>>>, collect::CcObjectSpace>;
+
+    let space = collect::CcObjectSpace::default();
+    assert_eq!(space.stats().objects_tracked, 0);
+
+    {
+        let a: List = space.create(RefCell::new(Vec::new()));
+        let b: List = space.create(RefCell::new(Vec::new()));
+        a.borrow_mut().push(Box::new(b.clone()));
+        b.borrow_mut().push(Box::new(a.clone()));
+    }
+    assert_eq!(space.stats().objects_tracked, 2);
+    assert_eq!(space.stats().collections, 0);
+
+    assert_eq!(space.collect_cycles(), 2);
+    let stats = space.stats();
+    assert_eq!(stats.objects_tracked, 2);
+    assert_eq!(stats.collections, 1);
+    assert_eq!(stats.objects_released, 2);
+
+    space.reset_stats();
+    let stats = space.stats();
+    assert_eq!(stats.objects_tracked, 0);
+    assert_eq!(stats.collections, 0);
+    assert_eq!(stats.objects_released, 0);
+}
+
+#[test]
+fn test_merge_collects_cross_space_cycle() {
+    type List = Cc<RefCell<Vec<Box<dyn Trace>>>, collect::CcObjectSpace>;
+
+    let space_a = collect::CcObjectSpace::default();
+    let space_b = collect::CcObjectSpace::default();
+    {
+        let a: List = space_a.create(RefCell::new(Vec::new()));
+        let b: List = space_b.create(RefCell::new(Vec::new()));
+        a.borrow_mut().push(Box::new(b.clone()));
+        b.borrow_mut().push(Box::new(a.clone()));
+    }
+    // Neither space can see that the cycle spans both of them.
+    assert_eq!(space_a.collect_cycles(), 0);
+    assert_eq!(space_b.collect_cycles(), 0);
+
+    space_a.merge(space_b);
+    assert_eq!(space_a.count_tracked(), 2);
+    assert_eq!(space_a.collect_cycles(), 2);
+}
+
 quickcheck! {
     fn test_quickcheck_16_vertex_graph(edges: Vec<u8>) -> bool {
         test_small_graph(16, &edges);
         true
     }
-}
\ No newline at end of file
+}